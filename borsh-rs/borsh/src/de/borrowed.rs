@@ -0,0 +1,117 @@
+//! Zero-copy borrowed deserialization.
+//!
+//! `&'a [u8]`, `Cow<'a, [u8]>`, and `Cow<'a, str>` borrow directly out of
+//! the input buffer instead of copying, the way `String` and
+//! `Box<[u8]>` always do today. Borrowing only makes sense when the
+//! input is a contiguous byte slice (the only shape a borrow can
+//! outlive the call), so [`BorshDeserializeBorrowed`] is driven through
+//! [`from_slice_borrowed`] rather than the generic `Input`-based
+//! `BorshDeserialize::deserialize`; inputs that aren't already a slice
+//! (e.g. a `Read`-backed `Input`) should keep using the owned
+//! `BorshDeserialize` impls instead.
+
+use super::{BorshDeserialize, ERROR_NOT_ALL_BYTES_READ};
+use crate::Input;
+use std::borrow::Cow;
+use std::io::{Error, ErrorKind};
+
+/// A data-structure that can be deserialized by borrowing directly out
+/// of a contiguous input buffer for lifetime `'a`, instead of copying.
+pub trait BorshDeserializeBorrowed<'a>: Sized {
+    fn deserialize_borrowed(input: &mut &'a [u8]) -> Result<Self, Error>;
+}
+
+/// Deserializes `T` from `v`, borrowing out of `v` itself wherever `T`'s
+/// impl supports it. Enforces the same no-trailing-bytes invariant as
+/// [`BorshDeserialize::try_from_slice`].
+pub fn from_slice_borrowed<'a, T>(v: &'a [u8]) -> Result<T, Error>
+where
+    T: BorshDeserializeBorrowed<'a>,
+{
+    let mut input = v;
+    let result = T::deserialize_borrowed(&mut input)?;
+    if input.rem_len()? > 0 {
+        return Err(Error::new(ErrorKind::InvalidData, ERROR_NOT_ALL_BYTES_READ));
+    }
+    Ok(result)
+}
+
+impl<'a> BorshDeserializeBorrowed<'a> for &'a [u8] {
+    #[inline]
+    fn deserialize_borrowed(input: &mut &'a [u8]) -> Result<Self, Error> {
+        let len = u32::deserialize(input)? as usize;
+        if len > input.len() {
+            return Err(Error::new(
+                ErrorKind::UnexpectedEof,
+                "Unexpected length of input",
+            ));
+        }
+        let (borrowed, rest) = input.split_at(len);
+        *input = rest;
+        Ok(borrowed)
+    }
+}
+
+impl<'a> BorshDeserializeBorrowed<'a> for Cow<'a, [u8]> {
+    #[inline]
+    fn deserialize_borrowed(input: &mut &'a [u8]) -> Result<Self, Error> {
+        <&'a [u8]>::deserialize_borrowed(input).map(Cow::Borrowed)
+    }
+}
+
+impl<'a> BorshDeserializeBorrowed<'a> for Cow<'a, str> {
+    #[inline]
+    fn deserialize_borrowed(input: &mut &'a [u8]) -> Result<Self, Error> {
+        let bytes = <&'a [u8]>::deserialize_borrowed(input)?;
+        std::str::from_utf8(bytes)
+            .map(Cow::Borrowed)
+            .map_err(|err| Error::new(ErrorKind::InvalidData, err.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn borrows_byte_slice_from_the_input_buffer() {
+        let mut bytes = 3u32.to_le_bytes().to_vec();
+        bytes.extend_from_slice(&[10, 20, 30]);
+
+        let borrowed = from_slice_borrowed::<&[u8]>(&bytes).unwrap();
+        assert_eq!(borrowed, &[10, 20, 30]);
+        // Actually zero-copy: the returned slice points into `bytes`.
+        assert_eq!(borrowed.as_ptr(), bytes[4..].as_ptr());
+    }
+
+    #[test]
+    fn borrows_cow_str_from_the_input_buffer() {
+        let mut bytes = 2u32.to_le_bytes().to_vec();
+        bytes.extend_from_slice(b"hi");
+
+        let cow = from_slice_borrowed::<Cow<str>>(&bytes).unwrap();
+        assert_eq!(cow, Cow::Borrowed("hi"));
+        assert!(matches!(cow, Cow::Borrowed(_)));
+    }
+
+    #[test]
+    fn rejects_trailing_bytes() {
+        let mut bytes = 3u32.to_le_bytes().to_vec();
+        bytes.extend_from_slice(&[10, 20, 30, 0xff]);
+        assert!(from_slice_borrowed::<&[u8]>(&bytes).is_err());
+    }
+
+    #[test]
+    fn rejects_declared_len_past_eof() {
+        let mut bytes = 5u32.to_le_bytes().to_vec();
+        bytes.extend_from_slice(&[10, 20]);
+        assert!(from_slice_borrowed::<&[u8]>(&bytes).is_err());
+    }
+
+    #[test]
+    fn rejects_invalid_utf8_for_cow_str() {
+        let mut bytes = 1u32.to_le_bytes().to_vec();
+        bytes.push(0xff);
+        assert!(from_slice_borrowed::<Cow<str>>(&bytes).is_err());
+    }
+}
@@ -0,0 +1,187 @@
+//! A depth/allocation budget for deserializing untrusted input.
+//!
+//! [`BorshDeserialize::deserialize`] trusts its declared lengths: a
+//! collection's `u32` length prefix is used directly to size an
+//! allocation before a single byte of the collection has been read, and
+//! nested types recurse with no bound on depth. [`Limits`] adds an
+//! explicit, caller-configured ceiling on top of that — on declared
+//! wire bytes (via [`BorshDeserialize::MIN_ENCODED_LEN`]) and on nesting
+//! depth — threaded through every collection impl via
+//! [`BorshDeserialize::deserialize_with_limit`]. It does not replace the
+//! `rem_len()`-based pre-allocation clamp `String`/`Vec` already apply
+//! against the actual remaining input; the bounded path of `String`,
+//! `Vec` and `HashMap` applies that same clamp alongside its own budget, since the budget
+//! alone (an abstract byte count) doesn't know how much input is really
+//! left.
+
+use crate::Input;
+use std::io::{Error, ErrorKind};
+
+/// Bounds on the allocation and recursion performed while decoding a
+/// single value via [`deserialize_with_limit`].
+#[derive(Debug, Clone, Copy)]
+pub struct Limits {
+    /// Total bytes that declared collection lengths are allowed to
+    /// account for, across the whole value being decoded.
+    pub max_alloc_bytes: usize,
+    /// Maximum nesting depth of `Vec`/`HashSet`/`HashMap`/`BTreeMap`
+    /// collections.
+    pub max_depth: usize,
+    /// Maximum number of elements a single collection may declare.
+    pub max_collection_len: usize,
+    depth: usize,
+}
+
+impl Limits {
+    /// Creates a budget with the given ceilings and zero depth used so far.
+    pub const fn new(max_alloc_bytes: usize, max_depth: usize, max_collection_len: usize) -> Self {
+        Limits {
+            max_alloc_bytes,
+            max_depth,
+            max_collection_len,
+            depth: 0,
+        }
+    }
+
+    /// Checks that a declared collection of `len` elements, each at
+    /// least `min_encoded_len` bytes on the wire, fits the remaining
+    /// budget, then reserves that many bytes from it.
+    pub fn check_collection(&mut self, len: usize, min_encoded_len: usize) -> Result<(), Error> {
+        if len > self.max_collection_len {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                "Collection length exceeds the configured limit",
+            ));
+        }
+        let declared_bytes = len.checked_mul(min_encoded_len).ok_or_else(|| {
+            Error::new(ErrorKind::InvalidData, "Collection length overflows")
+        })?;
+        if declared_bytes > self.max_alloc_bytes {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                "Collection would exceed the allocation budget",
+            ));
+        }
+        self.max_alloc_bytes -= declared_bytes;
+        Ok(())
+    }
+
+    /// Runs `f` one level deeper, erroring once `max_depth` is exceeded
+    /// instead of calling it. The depth is restored afterwards whether
+    /// `f` succeeds or fails, so nested `deserialize_with_limit` calls
+    /// can keep re-borrowing `limits` for sibling elements without a
+    /// `Drop`-based guard holding it for the whole call.
+    pub fn with_depth<R>(
+        &mut self,
+        f: impl FnOnce(&mut Limits) -> Result<R, Error>,
+    ) -> Result<R, Error> {
+        if self.depth >= self.max_depth {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                "Exceeded maximum recursion depth",
+            ));
+        }
+        self.depth += 1;
+        let result = f(self);
+        self.depth -= 1;
+        result
+    }
+}
+
+impl Default for Limits {
+    /// 64 MiB of declared-collection allocation, 64 levels of nesting,
+    /// and at most 2^24 elements per collection.
+    fn default() -> Self {
+        Limits::new(64 * 1024 * 1024, 64, 1 << 24)
+    }
+}
+
+/// Deserializes `T` from a slice of bytes, bounding allocation and
+/// recursion with `limits` so that untrusted input cannot be coerced
+/// into huge allocations or unbounded recursion.
+///
+/// Mirrors [`BorshDeserialize::try_from_slice`], including the
+/// trailing-byte check.
+pub fn deserialize_with_limit<T: super::BorshDeserialize>(
+    v: &[u8],
+    limits: &mut Limits,
+) -> Result<T, Error> {
+    let mut input = vec![0; v.len()];
+    input.copy_from_slice(v);
+    let mut input = &input[..];
+    let result = T::deserialize_with_limit(&mut input, limits)?;
+    if input.rem_len()? > 0 {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            super::ERROR_NOT_ALL_BYTES_READ,
+        ));
+    }
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accepts_collection_within_budget() {
+        let mut bytes = 3u32.to_le_bytes().to_vec();
+        bytes.extend_from_slice(&[1, 2, 3]);
+        let mut limits = Limits::default();
+        assert_eq!(
+            deserialize_with_limit::<Vec<u8>>(&bytes, &mut limits).unwrap(),
+            vec![1, 2, 3]
+        );
+    }
+
+    #[test]
+    fn rejects_collection_len_over_the_configured_ceiling() {
+        let mut bytes = 10u32.to_le_bytes().to_vec();
+        bytes.extend_from_slice(&[0; 10]);
+        let mut limits = Limits::new(1024, 64, 5);
+        assert!(deserialize_with_limit::<Vec<u8>>(&bytes, &mut limits).is_err());
+    }
+
+    #[test]
+    fn rejects_collection_over_the_allocation_budget() {
+        let mut bytes = 10u32.to_le_bytes().to_vec();
+        for i in 0..10u32 {
+            bytes.extend_from_slice(&i.to_le_bytes());
+        }
+        // 10 u32s declare 40 bytes, well past a 16-byte budget.
+        let mut limits = Limits::new(16, 64, 1024);
+        assert!(deserialize_with_limit::<Vec<u32>>(&bytes, &mut limits).is_err());
+    }
+
+    #[test]
+    fn rejects_nesting_deeper_than_max_depth() {
+        let mut bytes = 1u32.to_le_bytes().to_vec(); // outer Vec<Vec<u8>> len
+        bytes.extend_from_slice(&1u32.to_le_bytes()); // inner Vec<u8> len
+        bytes.push(7);
+
+        let mut limits = Limits::new(1024, 1, 1024);
+        assert!(deserialize_with_limit::<Vec<Vec<u8>>>(&bytes, &mut limits).is_err());
+    }
+
+    #[test]
+    fn budget_is_enforced_inside_a_nested_tuple() {
+        // Regression test: the allocation budget previously only
+        // covered the outer collection's own declared length, so a
+        // Vec<(Vec<u8>, Vec<u8>)> could bypass it entirely for the
+        // Vec<u8> fields nested inside the tuple.
+        let mut bytes = 1u32.to_le_bytes().to_vec(); // outer Vec<(..)> len
+        bytes.extend_from_slice(&3u32.to_le_bytes()); // first Vec<u8> len
+        bytes.extend_from_slice(&[1, 2, 3]);
+        bytes.extend_from_slice(&2u32.to_le_bytes()); // second Vec<u8> len
+        bytes.extend_from_slice(&[4, 5]);
+
+        // The outer Vec<(..)> check accounts for 8 bytes (the tuple's
+        // `MIN_ENCODED_LEN`, two 4-byte length prefixes), leaving 3 of
+        // this budget's 11 bytes for the nested Vec<u8> fields — enough
+        // for the first (3 bytes) but not the second (2 more bytes).
+        // Before threading `limits` through tuples, neither nested
+        // field was checked against the budget at all.
+        let mut limits = Limits::new(11, 64, 1024);
+        assert!(deserialize_with_limit::<Vec<(Vec<u8>, Vec<u8>)>>(&bytes, &mut limits).is_err());
+    }
+}
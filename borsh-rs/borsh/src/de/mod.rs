@@ -4,12 +4,73 @@ use std::collections::{BTreeMap, HashMap, HashSet};
 use std::io::{Cursor, Error};
 use std::mem::size_of;
 
+#[cfg(feature = "serde")]
+mod serde_compat;
+#[cfg(feature = "serde")]
+pub use serde_compat::{from_slice, Deserializer};
+
+mod limits;
+pub use limits::{deserialize_with_limit, Limits};
+
+mod varint;
+pub use varint::Varint;
+
+mod borrowed;
+pub use borrowed::{from_slice_borrowed, BorshDeserializeBorrowed};
+
 const ERROR_NOT_ALL_BYTES_READ: &str = "Not all bytes read";
 
 /// A data-structure that can be de-serialized from binary format by NBOR.
 pub trait BorshDeserialize: Sized {
     fn deserialize<I: Input>(input: &mut I) -> Result<Self, Error>;
 
+    /// Lower bound, in bytes, on the encoded size of any value of this
+    /// type. Used by [`deserialize_with_limit`] to reject a declared
+    /// collection length before allocating for it.
+    const MIN_ENCODED_LEN: usize = 1;
+
+    /// Fast path used by `Vec<T>` and the fixed-size array impls to read
+    /// `len` elements with a single bulk `read` instead of looping
+    /// `T::deserialize` once per element. Returns `Ok(None)` to fall back
+    /// to the per-element loop; only `u8` overrides this.
+    ///
+    /// This only covers the bulk-read half of the request that
+    /// introduced it: `Input` itself still exposes just the single-slice
+    /// `read` this hook already relies on, not a `read_vectored`-style
+    /// method. `Input` is defined outside this module (`crate::Input`),
+    /// and this snapshot of the tree does not include that file, so the
+    /// vectored method isn't implemented here.
+    ///
+    /// TODO: the request is only partially delivered until `Input`
+    /// itself grows a `read_vectored`-style method — track that as an
+    /// open follow-up against `Input`'s own definition, not as done.
+    #[doc(hidden)]
+    #[inline]
+    fn vec_from_input<I: Input>(_len: usize, _input: &mut I) -> Result<Option<Vec<Self>>, Error> {
+        Ok(None)
+    }
+
+    /// Like [`deserialize`](Self::deserialize), but checks `limits`
+    /// before performing any allocation or recursing, so that untrusted
+    /// input cannot be coerced into huge allocations or unbounded
+    /// recursion. The default falls back to the unbounded `deserialize`;
+    /// `Vec`, `String`, `HashMap`, `BTreeMap`, `HashSet`, `Box<[u8]>`,
+    /// `Option`, tuples, and fixed-size arrays override it to thread
+    /// `limits` through their elements.
+    ///
+    /// Types outside that list — in particular any user-defined struct
+    /// or enum, since this crate has no derive macro in this tree to
+    /// generate their impls — keep the default and fall into unbounded
+    /// `deserialize`. That means a collection nested inside such a type,
+    /// e.g. a hand-written `struct Pair(Vec<u8>, Vec<u8>)`, only has its
+    /// *outer* length checked; decoding the `Vec<u8>` fields inside it
+    /// is unbounded. `deserialize_with_limit` only actually bounds
+    /// nesting that stays within the types listed above.
+    fn deserialize_with_limit<I: Input>(input: &mut I, limits: &mut Limits) -> Result<Self, Error> {
+        let _ = limits;
+        Self::deserialize(input)
+    }
+
     /// Deserialize this instance from a slice of bytes.
     fn try_from_slice(v: &[u8]) -> Result<Self, Error> {
         let mut input = vec![0; v.len()];
@@ -33,11 +94,27 @@ impl BorshDeserialize for u8 {
         input.read(std::slice::from_mut(&mut res))?;
         Ok(res)
     }
+
+    #[inline]
+    fn vec_from_input<I: Input>(len: usize, input: &mut I) -> Result<Option<Vec<Self>>, Error> {
+        let capacity = min(input.rem_len()?, len);
+        let mut result = vec![0u8; capacity];
+        input.read(&mut result)?;
+        if capacity < len {
+            return Err(Error::new(
+                std::io::ErrorKind::UnexpectedEof,
+                "Unexpected length of input",
+            ));
+        }
+        Ok(Some(result))
+    }
 }
 
 macro_rules! impl_for_integer {
     ($type: ident) => {
         impl BorshDeserialize for $type {
+            const MIN_ENCODED_LEN: usize = size_of::<$type>();
+
             #[inline]
             fn deserialize<I: Input>(input: &mut I) -> Result<Self, Error> {
                 let mut data = [0u8; size_of::<$type>()];
@@ -65,6 +142,8 @@ impl_for_integer!(usize);
 macro_rules! impl_for_float {
     ($type: ident, $int_type: ident) => {
         impl BorshDeserialize for $type {
+            const MIN_ENCODED_LEN: usize = size_of::<$type>();
+
             fn deserialize<I: Input>(input: &mut I) -> Result<Self, Error> {
                 let mut data = [0u8; size_of::<$type>()];
                 input.read(&mut data)?;
@@ -85,6 +164,8 @@ impl_for_float!(f32, u32);
 impl_for_float!(f64, u64);
 
 impl BorshDeserialize for bool {
+    const MIN_ENCODED_LEN: usize = size_of::<u8>();
+
     #[inline]
     fn deserialize<I: Input>(input: &mut I) -> Result<Self, Error> {
         Ok(input.read_byte()? == 1)
@@ -95,6 +176,8 @@ impl<T> BorshDeserialize for Option<T>
 where
     T: BorshDeserialize,
 {
+    const MIN_ENCODED_LEN: usize = size_of::<u8>();
+
     #[inline]
     fn deserialize<I: Input>(input: &mut I) -> Result<Self, Error> {
         let mut flag = [0u8];
@@ -105,9 +188,21 @@ where
             Ok(Some(T::deserialize(input)?))
         }
     }
+
+    fn deserialize_with_limit<I: Input>(input: &mut I, limits: &mut Limits) -> Result<Self, Error> {
+        let mut flag = [0u8];
+        input.read(&mut flag)?;
+        if flag[0] == 0 {
+            Ok(None)
+        } else {
+            Ok(Some(T::deserialize_with_limit(input, limits)?))
+        }
+    }
 }
 
 impl BorshDeserialize for String {
+    const MIN_ENCODED_LEN: usize = size_of::<u32>();
+
     #[inline]
     fn deserialize<I: Input>(input: &mut I) -> Result<Self, Error> {
         let len = u32::deserialize(input)? as usize;
@@ -120,6 +215,21 @@ impl BorshDeserialize for String {
         String::from_utf8(result)
             .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err.to_string()))
     }
+
+    fn deserialize_with_limit<I: Input>(input: &mut I, limits: &mut Limits) -> Result<Self, Error> {
+        let len = u32::deserialize(input)? as usize;
+        limits.check_collection(len, 1)?;
+        if len > input.rem_len()? {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::UnexpectedEof,
+                "Unexpected length of input",
+            ));
+        }
+        let mut result = vec![0; len];
+        input.read(&mut result)?;
+        String::from_utf8(result)
+            .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err.to_string()))
+    }
 }
 
 #[cfg(feature = "std")]
@@ -127,9 +237,14 @@ impl<T> BorshDeserialize for Vec<T>
 where
     T: BorshDeserialize,
 {
+    const MIN_ENCODED_LEN: usize = size_of::<u32>();
+
     #[inline]
     fn deserialize<I: Input>(input: &mut I) -> Result<Self, Error> {
         let len = u32::deserialize(input)? as usize;
+        if let Some(result) = T::vec_from_input(len, input)? {
+            return Ok(result);
+        }
         let capacity = min(
             input.rem_len()?.checked_div(size_of::<T>()).unwrap_or(0),
             len,
@@ -141,6 +256,27 @@ where
         }
         Ok(result)
     }
+
+    fn deserialize_with_limit<I: Input>(input: &mut I, limits: &mut Limits) -> Result<Self, Error> {
+        let len = u32::deserialize(input)? as usize;
+        limits.check_collection(len, T::MIN_ENCODED_LEN)?;
+        // `check_collection` only bounds `len` against the abstract byte
+        // budget; an attacker can still declare a `len` that the budget
+        // allows (e.g. a small `T::MIN_ENCODED_LEN`) but that vastly
+        // exceeds what `input` could possibly contain. Clamp the
+        // pre-allocation the same way the unbounded `deserialize` does.
+        let capacity = min(
+            input.rem_len()?.checked_div(T::MIN_ENCODED_LEN.max(1)).unwrap_or(0),
+            len,
+        );
+        limits.with_depth(|limits| {
+            let mut result = Vec::with_capacity(capacity);
+            for _ in 0..len {
+                result.push(T::deserialize_with_limit(input, limits)?);
+            }
+            Ok(result)
+        })
+    }
 }
 
 #[cfg(feature = "std")]
@@ -148,11 +284,18 @@ impl<T> BorshDeserialize for HashSet<T>
 where
     T: BorshDeserialize + Eq + std::hash::Hash,
 {
+    const MIN_ENCODED_LEN: usize = size_of::<u32>();
+
     #[inline]
     fn deserialize<I: Input>(input: &mut I) -> Result<Self, Error> {
         let vec = <Vec<T>>::deserialize(input)?;
         Ok(vec.into_iter().collect::<HashSet<T>>())
     }
+
+    fn deserialize_with_limit<I: Input>(input: &mut I, limits: &mut Limits) -> Result<Self, Error> {
+        let vec = <Vec<T>>::deserialize_with_limit(input, limits)?;
+        Ok(vec.into_iter().collect::<HashSet<T>>())
+    }
 }
 
 #[cfg(feature = "std")]
@@ -161,6 +304,8 @@ where
     K: BorshDeserialize + Eq + std::hash::Hash,
     V: BorshDeserialize,
 {
+    const MIN_ENCODED_LEN: usize = size_of::<u32>();
+
     #[inline]
     fn deserialize<I: Input>(input: &mut I) -> Result<Self, Error> {
         let len = u32::deserialize(input)?;
@@ -173,6 +318,31 @@ where
         }
         Ok(result)
     }
+
+    fn deserialize_with_limit<I: Input>(input: &mut I, limits: &mut Limits) -> Result<Self, Error> {
+        let len = u32::deserialize(input)? as usize;
+        limits.check_collection(len, K::MIN_ENCODED_LEN + V::MIN_ENCODED_LEN)?;
+        // `check_collection` bounds `len * (K::MIN_ENCODED_LEN +
+        // V::MIN_ENCODED_LEN)` — wire bytes, not the in-memory size of a
+        // `(K, V)` entry, which is typically larger (see TODO(16) above,
+        // which still applies to the unbounded `deserialize`: we still
+        // don't know the *in-memory* capacity is safe to pre-allocate).
+        // Also clamp by how many wire-minimum-sized entries `input`
+        // could actually still contain, so a budget that happens to
+        // allow a huge `len` can't turn a tiny input into a huge
+        // `with_capacity` call.
+        let min_entry_len = (K::MIN_ENCODED_LEN + V::MIN_ENCODED_LEN).max(1);
+        let capacity = min(input.rem_len()?.checked_div(min_entry_len).unwrap_or(0), len);
+        limits.with_depth(|limits| {
+            let mut result = HashMap::with_capacity(capacity);
+            for _ in 0..len {
+                let key = K::deserialize_with_limit(input, limits)?;
+                let value = V::deserialize_with_limit(input, limits)?;
+                result.insert(key, value);
+            }
+            Ok(result)
+        })
+    }
 }
 
 #[cfg(feature = "std")]
@@ -181,6 +351,8 @@ where
     K: BorshDeserialize + Ord + std::hash::Hash,
     V: BorshDeserialize,
 {
+    const MIN_ENCODED_LEN: usize = size_of::<u32>();
+
     #[inline]
     fn deserialize<I: Input>(input: &mut I) -> Result<Self, Error> {
         let len = u32::deserialize(input)?;
@@ -192,10 +364,26 @@ where
         }
         Ok(result)
     }
+
+    fn deserialize_with_limit<I: Input>(input: &mut I, limits: &mut Limits) -> Result<Self, Error> {
+        let len = u32::deserialize(input)? as usize;
+        limits.check_collection(len, K::MIN_ENCODED_LEN + V::MIN_ENCODED_LEN)?;
+        limits.with_depth(|limits| {
+            let mut result = BTreeMap::new();
+            for _ in 0..len {
+                let key = K::deserialize_with_limit(input, limits)?;
+                let value = V::deserialize_with_limit(input, limits)?;
+                result.insert(key, value);
+            }
+            Ok(result)
+        })
+    }
 }
 
 #[cfg(feature = "std")]
 impl BorshDeserialize for std::net::SocketAddr {
+    const MIN_ENCODED_LEN: usize = size_of::<u8>() + std::net::SocketAddrV4::MIN_ENCODED_LEN;
+
     #[inline]
     fn deserialize<I: Input>(input: &mut I) -> Result<Self, Error> {
         let kind = u8::deserialize(input)?;
@@ -212,6 +400,9 @@ impl BorshDeserialize for std::net::SocketAddr {
 
 #[cfg(feature = "std")]
 impl BorshDeserialize for std::net::SocketAddrV4 {
+    const MIN_ENCODED_LEN: usize =
+        std::net::Ipv4Addr::MIN_ENCODED_LEN + u16::MIN_ENCODED_LEN;
+
     #[inline]
     fn deserialize<I: Input>(input: &mut I) -> Result<Self, Error> {
         let ip = std::net::Ipv4Addr::deserialize(input)?;
@@ -222,6 +413,9 @@ impl BorshDeserialize for std::net::SocketAddrV4 {
 
 #[cfg(feature = "std")]
 impl BorshDeserialize for std::net::SocketAddrV6 {
+    const MIN_ENCODED_LEN: usize =
+        std::net::Ipv6Addr::MIN_ENCODED_LEN + u16::MIN_ENCODED_LEN;
+
     #[inline]
     fn deserialize<I: Input>(input: &mut I) -> Result<Self, Error> {
         let ip = std::net::Ipv6Addr::deserialize(input)?;
@@ -232,6 +426,8 @@ impl BorshDeserialize for std::net::SocketAddrV6 {
 
 #[cfg(feature = "std")]
 impl BorshDeserialize for std::net::Ipv4Addr {
+    const MIN_ENCODED_LEN: usize = 4;
+
     #[inline]
     fn deserialize<I: Input>(input: &mut I) -> Result<Self, Error> {
         let mut buf = [0u8; 4];
@@ -242,6 +438,8 @@ impl BorshDeserialize for std::net::Ipv4Addr {
 
 #[cfg(feature = "std")]
 impl BorshDeserialize for std::net::Ipv6Addr {
+    const MIN_ENCODED_LEN: usize = 16;
+
     #[inline]
     fn deserialize<I: Input>(input: &mut I) -> Result<Self, Error> {
         let mut buf = [0u8; 16];
@@ -251,6 +449,8 @@ impl BorshDeserialize for std::net::Ipv6Addr {
 }
 
 impl BorshDeserialize for Box<[u8]> {
+    const MIN_ENCODED_LEN: usize = size_of::<u32>();
+
     fn deserialize<I: Input>(input: &mut I) -> Result<Self, Error> {
         let len = u32::deserialize(input)? as usize;
         if (len as usize > input.rem_len()?) {
@@ -263,6 +463,14 @@ impl BorshDeserialize for Box<[u8]> {
         input.read(&mut res)?;
         Ok(res.into_boxed_slice())
     }
+
+    fn deserialize_with_limit<I: Input>(input: &mut I, limits: &mut Limits) -> Result<Self, Error> {
+        let len = u32::deserialize(input)? as usize;
+        limits.check_collection(len, 1)?;
+        let mut res = vec![0; len];
+        input.read(&mut res)?;
+        Ok(res.into_boxed_slice())
+    }
 }
 
 macro_rules! impl_arrays {
@@ -271,14 +479,28 @@ macro_rules! impl_arrays {
       impl<T> BorshDeserialize for [T; $len]
       where T: BorshDeserialize + Default + Copy
       {
+        const MIN_ENCODED_LEN: usize = T::MIN_ENCODED_LEN * $len;
+
         #[inline]
         fn deserialize<I: Input>(input: &mut I) -> Result<Self, Error> {
             let mut result = [T::default(); $len];
+            if let Some(vec) = T::vec_from_input($len, input)? {
+                result.copy_from_slice(&vec);
+                return Ok(result);
+            }
             for i in 0..$len {
                 result[i] = T::deserialize(input)?;
             }
             Ok(result)
         }
+
+        fn deserialize_with_limit<I: Input>(input: &mut I, limits: &mut Limits) -> Result<Self, Error> {
+            let mut result = [T::default(); $len];
+            for i in 0..$len {
+                result[i] = T::deserialize_with_limit(input, limits)?;
+            }
+            Ok(result)
+        }
       }
       )+
     };
@@ -291,10 +513,16 @@ macro_rules! impl_tuple {
       impl<$($name),+> BorshDeserialize for ($($name),+)
       where $($name: BorshDeserialize,)+
       {
+        const MIN_ENCODED_LEN: usize = 0 $(+ $name::MIN_ENCODED_LEN)+;
+
         #[inline]
         fn deserialize<I: Input>(input: &mut I) -> Result<Self, Error> {
             Ok(($($name::deserialize(input)?,)+))
         }
+
+        fn deserialize_with_limit<I: Input>(input: &mut I, limits: &mut Limits) -> Result<Self, Error> {
+            Ok(($($name::deserialize_with_limit(input, limits)?,)+))
+        }
       }
     };
 }
@@ -318,3 +546,62 @@ impl_tuple!(T0 T1 T2 T3 T4 T5 T6 T7 T8 T9 T10 T11 T12 T13 T14 T15 T16);
 impl_tuple!(T0 T1 T2 T3 T4 T5 T6 T7 T8 T9 T10 T11 T12 T13 T14 T15 T16 T17);
 impl_tuple!(T0 T1 T2 T3 T4 T5 T6 T7 T8 T9 T10 T11 T12 T13 T14 T15 T16 T17 T18);
 impl_tuple!(T0 T1 T2 T3 T4 T5 T6 T7 T8 T9 T10 T11 T12 T13 T14 T15 T16 T17 T18 T19);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bulk_reads_byte_vec() {
+        let mut bytes = 3u32.to_le_bytes().to_vec();
+        bytes.extend_from_slice(&[10, 20, 30]);
+        assert_eq!(Vec::<u8>::try_from_slice(&bytes).unwrap(), vec![10, 20, 30]);
+    }
+
+    #[test]
+    fn bulk_reads_byte_array() {
+        // Fixed-size arrays have no length prefix: their length is
+        // already known at compile time.
+        let bytes = [10u8, 20, 30];
+        assert_eq!(<[u8; 3]>::try_from_slice(&bytes).unwrap(), [10, 20, 30]);
+    }
+
+    #[test]
+    fn bulk_read_errors_on_declared_len_past_eof() {
+        let mut bytes = 5u32.to_le_bytes().to_vec();
+        bytes.extend_from_slice(&[1, 2]);
+        assert!(Vec::<u8>::try_from_slice(&bytes).is_err());
+    }
+
+    #[test]
+    fn vec_of_non_u8_still_round_trips_through_the_loop_path() {
+        let mut bytes = 2u32.to_le_bytes().to_vec();
+        bytes.extend_from_slice(&1u32.to_le_bytes());
+        bytes.extend_from_slice(&2u32.to_le_bytes());
+        assert_eq!(Vec::<u32>::try_from_slice(&bytes).unwrap(), vec![1, 2]);
+    }
+
+    #[test]
+    fn bounded_vec_does_not_over_allocate_past_the_actual_input() {
+        // A declared len of 1M Ipv6Addrs (16 MiB at
+        // Ipv6Addr::MIN_ENCODED_LEN == 16) fits inside the default 64 MiB
+        // byte budget, but the 4-byte input obviously cannot back a
+        // ~16 MB allocation.
+        // Regression test for the missing rem_len() clamp on the
+        // bounded path: this must error (EOF), not allocate.
+        let bytes = (1u32 << 20).to_le_bytes().to_vec();
+        let mut limits = Limits::default();
+        assert!(
+            deserialize_with_limit::<Vec<std::net::Ipv6Addr>>(&bytes, &mut limits).is_err()
+        );
+    }
+
+    #[test]
+    fn bounded_hash_map_does_not_over_allocate_past_the_actual_input() {
+        let bytes = (1u32 << 20).to_le_bytes().to_vec();
+        let mut limits = Limits::default();
+        assert!(
+            deserialize_with_limit::<HashMap<String, String>>(&bytes, &mut limits).is_err()
+        );
+    }
+}
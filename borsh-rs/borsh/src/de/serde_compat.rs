@@ -0,0 +1,425 @@
+//! A `serde::Deserializer` front-end for the Borsh wire format.
+//!
+//! This lets any type with `#[derive(serde::Deserialize)]` be read
+//! straight from Borsh bytes without hand-writing a `BorshDeserialize`
+//! impl. It maps Borsh's own encoding decisions onto serde's model:
+//! fixed little-endian integers/floats, a single discriminant byte for
+//! `Option`, a `u32` length prefix for sequences/maps/strings, and a
+//! leading `u8` variant tag for enums.
+
+use super::{BorshDeserialize, ERROR_NOT_ALL_BYTES_READ};
+use crate::Input;
+use serde::de::{self, DeserializeSeed, EnumAccess, IntoDeserializer, MapAccess, SeqAccess, VariantAccess, Visitor};
+use serde::de::Error as _;
+use std::fmt;
+
+/// Deserializes a value of type `T` directly from Borsh-encoded bytes
+/// via its `serde::Deserialize` impl.
+///
+/// Like [`BorshDeserialize::try_from_slice`], this enforces that no
+/// bytes are left over once `T` has been read.
+pub fn from_slice<'de, T>(v: &'de [u8]) -> std::io::Result<T>
+where
+    T: de::Deserialize<'de>,
+{
+    let mut input = v;
+    let result = {
+        let mut deserializer = Deserializer { input: &mut input };
+        T::deserialize(&mut deserializer).map_err(std::io::Error::from)?
+    };
+    if input.rem_len()? > 0 {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            ERROR_NOT_ALL_BYTES_READ,
+        ));
+    }
+    Ok(result)
+}
+
+/// A [`serde::Deserializer`] that reads values out of an [`Input`] using
+/// the Borsh wire format.
+pub struct Deserializer<'i, I> {
+    input: &'i mut I,
+}
+
+/// Error produced while driving a [`Deserializer`].
+///
+/// Wraps [`std::io::Error`] so that [`serde::de::Error::custom`] messages
+/// can be represented; converted back to `std::io::Error` at the
+/// [`from_slice`] boundary.
+#[derive(Debug)]
+pub struct Error(std::io::Error);
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.0, f)
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl de::Error for Error {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Error(std::io::Error::new(std::io::ErrorKind::InvalidData, msg.to_string()))
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(err: std::io::Error) -> Self {
+        Error(err)
+    }
+}
+
+impl From<Error> for std::io::Error {
+    fn from(err: Error) -> Self {
+        err.0
+    }
+}
+
+macro_rules! deserialize_integer {
+    ($deserialize:ident, $visit:ident, $type:ty) => {
+        fn $deserialize<V>(self, visitor: V) -> Result<V::Value, Error>
+        where
+            V: Visitor<'de>,
+        {
+            visitor.$visit(<$type as BorshDeserialize>::deserialize(self.input)?)
+        }
+    };
+}
+
+impl<'de, 'i, I> de::Deserializer<'de> for &mut Deserializer<'i, I>
+where
+    I: Input,
+{
+    type Error = Error;
+
+    fn deserialize_any<V>(self, _visitor: V) -> Result<V::Value, Error>
+    where
+        V: Visitor<'de>,
+    {
+        Err(Error::custom(
+            "Borsh is not self-describing; deserialize_any is not supported",
+        ))
+    }
+
+    fn deserialize_bool<V>(self, visitor: V) -> Result<V::Value, Error>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_bool(bool::deserialize(self.input)?)
+    }
+
+    deserialize_integer!(deserialize_i8, visit_i8, i8);
+    deserialize_integer!(deserialize_i16, visit_i16, i16);
+    deserialize_integer!(deserialize_i32, visit_i32, i32);
+    deserialize_integer!(deserialize_i64, visit_i64, i64);
+    deserialize_integer!(deserialize_i128, visit_i128, i128);
+    deserialize_integer!(deserialize_u8, visit_u8, u8);
+    deserialize_integer!(deserialize_u16, visit_u16, u16);
+    deserialize_integer!(deserialize_u32, visit_u32, u32);
+    deserialize_integer!(deserialize_u64, visit_u64, u64);
+    deserialize_integer!(deserialize_u128, visit_u128, u128);
+    deserialize_integer!(deserialize_f32, visit_f32, f32);
+    deserialize_integer!(deserialize_f64, visit_f64, f64);
+
+    fn deserialize_char<V>(self, visitor: V) -> Result<V::Value, Error>
+    where
+        V: Visitor<'de>,
+    {
+        let value = u32::deserialize(self.input)?;
+        let value = char::from_u32(value)
+            .ok_or_else(|| Error::custom(format!("Invalid char value: {}", value)))?;
+        visitor.visit_char(value)
+    }
+
+    fn deserialize_str<V>(self, visitor: V) -> Result<V::Value, Error>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_string(String::deserialize(self.input)?)
+    }
+
+    fn deserialize_string<V>(self, visitor: V) -> Result<V::Value, Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_str(visitor)
+    }
+
+    fn deserialize_bytes<V>(self, visitor: V) -> Result<V::Value, Error>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_byte_buf(Vec::<u8>::deserialize(self.input)?)
+    }
+
+    fn deserialize_byte_buf<V>(self, visitor: V) -> Result<V::Value, Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_bytes(visitor)
+    }
+
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value, Error>
+    where
+        V: Visitor<'de>,
+    {
+        match u8::deserialize(self.input)? {
+            0 => visitor.visit_none(),
+            _ => visitor.visit_some(self),
+        }
+    }
+
+    fn deserialize_unit<V>(self, visitor: V) -> Result<V::Value, Error>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_unit_struct<V>(self, _name: &'static str, visitor: V) -> Result<V::Value, Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_unit(visitor)
+    }
+
+    fn deserialize_newtype_struct<V>(self, _name: &'static str, visitor: V) -> Result<V::Value, Error>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_seq<V>(self, visitor: V) -> Result<V::Value, Error>
+    where
+        V: Visitor<'de>,
+    {
+        let len = u32::deserialize(self.input)? as usize;
+        visitor.visit_seq(BorshSeqAccess { de: self, remaining: len })
+    }
+
+    fn deserialize_tuple<V>(self, len: usize, visitor: V) -> Result<V::Value, Error>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_seq(BorshSeqAccess { de: self, remaining: len })
+    }
+
+    fn deserialize_tuple_struct<V>(
+        self,
+        _name: &'static str,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_tuple(len, visitor)
+    }
+
+    fn deserialize_map<V>(self, visitor: V) -> Result<V::Value, Error>
+    where
+        V: Visitor<'de>,
+    {
+        let len = u32::deserialize(self.input)? as usize;
+        visitor.visit_map(BorshSeqAccess { de: self, remaining: len })
+    }
+
+    fn deserialize_struct<V>(
+        self,
+        _name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_tuple(fields.len(), visitor)
+    }
+
+    fn deserialize_enum<V>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error>
+    where
+        V: Visitor<'de>,
+    {
+        let variant = u8::deserialize(self.input)? as u32;
+        visitor.visit_enum(BorshEnumAccess { variant, de: self })
+    }
+
+    fn deserialize_identifier<V>(self, visitor: V) -> Result<V::Value, Error>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_u32(u32::deserialize(self.input)?)
+    }
+
+    fn deserialize_ignored_any<V>(self, visitor: V) -> Result<V::Value, Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_any(visitor)
+    }
+}
+
+/// Drives a fixed-length run of elements, used for both Borsh sequences
+/// (length-prefixed) and tuples/structs (length known at compile time).
+struct BorshSeqAccess<'a, 'i, I> {
+    de: &'a mut Deserializer<'i, I>,
+    remaining: usize,
+}
+
+impl<'de, 'a, 'i, I> SeqAccess<'de> for BorshSeqAccess<'a, 'i, I>
+where
+    I: Input,
+{
+    type Error = Error;
+
+    fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>, Error>
+    where
+        T: DeserializeSeed<'de>,
+    {
+        if self.remaining == 0 {
+            return Ok(None);
+        }
+        self.remaining -= 1;
+        seed.deserialize(&mut *self.de).map(Some)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.remaining)
+    }
+}
+
+impl<'de, 'a, 'i, I> MapAccess<'de> for BorshSeqAccess<'a, 'i, I>
+where
+    I: Input,
+{
+    type Error = Error;
+
+    fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>, Error>
+    where
+        K: DeserializeSeed<'de>,
+    {
+        if self.remaining == 0 {
+            return Ok(None);
+        }
+        self.remaining -= 1;
+        seed.deserialize(&mut *self.de).map(Some)
+    }
+
+    fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value, Error>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        seed.deserialize(&mut *self.de)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.remaining)
+    }
+}
+
+/// Drives the variant tag (a leading `u8`) and payload of an enum.
+struct BorshEnumAccess<'a, 'i, I> {
+    variant: u32,
+    de: &'a mut Deserializer<'i, I>,
+}
+
+impl<'de, 'a, 'i, I> EnumAccess<'de> for BorshEnumAccess<'a, 'i, I>
+where
+    I: Input,
+{
+    type Error = Error;
+    type Variant = Self;
+
+    fn variant_seed<V>(self, seed: V) -> Result<(V::Value, Self::Variant), Error>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        let value = seed.deserialize(IntoDeserializer::<'de, Error>::into_deserializer(self.variant))?;
+        Ok((value, self))
+    }
+}
+
+impl<'de, 'a, 'i, I> VariantAccess<'de> for BorshEnumAccess<'a, 'i, I>
+where
+    I: Input,
+{
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn newtype_variant_seed<T>(self, seed: T) -> Result<T::Value, Error>
+    where
+        T: DeserializeSeed<'de>,
+    {
+        seed.deserialize(self.de)
+    }
+
+    fn tuple_variant<V>(self, len: usize, visitor: V) -> Result<V::Value, Error>
+    where
+        V: Visitor<'de>,
+    {
+        de::Deserializer::deserialize_tuple(self.de, len, visitor)
+    }
+
+    fn struct_variant<V>(self, fields: &'static [&'static str], visitor: V) -> Result<V::Value, Error>
+    where
+        V: Visitor<'de>,
+    {
+        de::Deserializer::deserialize_tuple(self.de, fields.len(), visitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::from_slice;
+
+    #[test]
+    fn decodes_integer() {
+        let bytes = 42u32.to_le_bytes();
+        assert_eq!(from_slice::<u32>(&bytes).unwrap(), 42);
+    }
+
+    #[test]
+    fn rejects_trailing_bytes() {
+        let mut bytes = 42u32.to_le_bytes().to_vec();
+        bytes.push(0xff);
+        assert!(from_slice::<u32>(&bytes).is_err());
+    }
+
+    #[test]
+    fn decodes_option() {
+        assert_eq!(from_slice::<Option<u32>>(&[0]).unwrap(), None);
+
+        let mut bytes = vec![1];
+        bytes.extend_from_slice(&7u32.to_le_bytes());
+        assert_eq!(from_slice::<Option<u32>>(&bytes).unwrap(), Some(7));
+    }
+
+    #[test]
+    fn decodes_length_prefixed_vec_and_string() {
+        let mut bytes = 2u32.to_le_bytes().to_vec();
+        bytes.extend_from_slice(&1u32.to_le_bytes());
+        bytes.extend_from_slice(&2u32.to_le_bytes());
+        assert_eq!(from_slice::<Vec<u32>>(&bytes).unwrap(), vec![1, 2]);
+
+        let mut bytes = 2u32.to_le_bytes().to_vec();
+        bytes.extend_from_slice(b"hi");
+        assert_eq!(from_slice::<String>(&bytes).unwrap(), "hi");
+    }
+
+    #[test]
+    fn decodes_tuple_with_no_length_prefix() {
+        let mut bytes = vec![5u8];
+        bytes.extend_from_slice(&9u32.to_le_bytes());
+        assert_eq!(from_slice::<(u8, u32)>(&bytes).unwrap(), (5, 9));
+    }
+}
@@ -0,0 +1,203 @@
+//! Opt-in variable-length integer decoding.
+//!
+//! [`Varint<T>`] swaps `T`'s fixed-width little-endian wire format (the
+//! one `impl_for_integer!` produces) for unsigned LEB128, with zig-zag
+//! mapping for signed types, so a small integer spends close to 1 byte
+//! instead of always paying for the full width.
+//!
+//! This crate is deserialize-only, so only the decode half exists here
+//! (there is no matching `BorshSerialize` encoder to swap in this
+//! module).
+//!
+//! TODO: this module is a partial delivery of the request that
+//! introduced it, not the full feature — track the rest as open
+//! follow-ups rather than considering the request closed:
+//! - Varint-encoded length prefixes for `Vec`/`String`/map collections
+//!   (those still always spend a fixed `u32`).
+//! - A decoder-mode flag on `Input` to select fixed-width vs. varint
+//!   decoding implicitly.
+//!
+//! Both would require changes to `Input` itself (`crate::Input`), which
+//! lives outside this module and isn't part of this tree snapshot; only
+//! the standalone `Varint<T>` wrapper, which doesn't need `Input` to
+//! change, was in reach from here. The space-saving payoff the request
+//! was motivated by only lands once the length-prefix integration above
+//! is done — `Varint<T>` alone only helps callers who opt individual
+//! integer fields into it by hand.
+
+use super::BorshDeserialize;
+use crate::Input;
+use std::io::{Error, ErrorKind};
+use std::mem::size_of;
+
+/// Reads `T` as an unsigned LEB128 varint (zig-zag mapped for signed
+/// `T`) instead of `T`'s usual fixed-width encoding.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Varint<T>(pub T);
+
+impl<T> From<T> for Varint<T> {
+    fn from(value: T) -> Self {
+        Varint(value)
+    }
+}
+
+/// Maps a primitive integer type onto the raw unsigned LEB128 decode:
+/// its bit width (to bound the number of continuation bytes and to
+/// reject values that don't fit) and, for signed types, the zig-zag
+/// unmapping.
+trait LebInteger: Sized {
+    const BITS: u32;
+    fn from_uleb128(raw: u128) -> Self;
+}
+
+macro_rules! impl_leb_unsigned {
+    ($type:ty) => {
+        impl LebInteger for $type {
+            const BITS: u32 = (size_of::<$type>() * 8) as u32;
+
+            #[inline]
+            fn from_uleb128(raw: u128) -> Self {
+                raw as $type
+            }
+        }
+    };
+}
+
+macro_rules! impl_leb_signed {
+    ($type:ty) => {
+        impl LebInteger for $type {
+            const BITS: u32 = (size_of::<$type>() * 8) as u32;
+
+            #[inline]
+            fn from_uleb128(raw: u128) -> Self {
+                let zigzag = (raw >> 1) as i128 ^ -((raw & 1) as i128);
+                zigzag as $type
+            }
+        }
+    };
+}
+
+impl_leb_unsigned!(u8);
+impl_leb_unsigned!(u16);
+impl_leb_unsigned!(u32);
+impl_leb_unsigned!(u64);
+impl_leb_unsigned!(u128);
+impl_leb_unsigned!(usize);
+impl_leb_signed!(i8);
+impl_leb_signed!(i16);
+impl_leb_signed!(i32);
+impl_leb_signed!(i64);
+impl_leb_signed!(i128);
+impl_leb_signed!(isize);
+
+/// Decodes an unsigned LEB128 group stream into `T`: the low 7 bits of
+/// each byte are shifted into the result at increasing 7-bit offsets,
+/// stopping at the first byte whose continuation bit (`0x80`) is clear.
+///
+/// Rejects non-canonical encodings (a trailing continuation byte that
+/// contributes no bits, or a value that does not fit `T`) and caps the
+/// number of continuation bytes at `ceil(T::BITS / 7)` so that malformed
+/// input cannot read forever.
+fn decode_leb128<T: LebInteger, I: Input>(input: &mut I) -> Result<T, Error> {
+    let max_groups = ((T::BITS + 6) / 7) as usize;
+    let mut result: u128 = 0;
+    let mut shift = 0u32;
+    for group in 0..max_groups {
+        let byte = u8::deserialize(input)?;
+        let low_bits = (byte & 0x7f) as u128;
+        result |= low_bits << shift;
+        if byte & 0x80 == 0 {
+            if group > 0 && low_bits == 0 {
+                return Err(Error::new(
+                    ErrorKind::InvalidData,
+                    "Non-canonical LEB128 encoding: trailing continuation byte adds no bits",
+                ));
+            }
+            if shift + 7 > T::BITS {
+                // This group carries bits past `T::BITS`; check the ones
+                // this group itself would drop directly, rather than
+                // masking `result` (a `result`-based mask can't express
+                // "drop everything" for `T::BITS == 128`, since shifting
+                // a `u128` mask left by 128 is a no-op, not zero).
+                let valid_bits = T::BITS - shift;
+                if low_bits >> valid_bits != 0 {
+                    return Err(Error::new(
+                        ErrorKind::InvalidData,
+                        "LEB128 value does not fit the target integer width",
+                    ));
+                }
+            }
+            return Ok(T::from_uleb128(result));
+        }
+        shift += 7;
+    }
+    Err(Error::new(
+        ErrorKind::InvalidData,
+        "LEB128 value has more continuation bytes than its width allows",
+    ))
+}
+
+impl<T> BorshDeserialize for Varint<T>
+where
+    T: LebInteger,
+{
+    #[inline]
+    fn deserialize<I: Input>(input: &mut I) -> Result<Self, Error> {
+        decode_leb128::<T, I>(input).map(Varint)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decodes_single_byte_values() {
+        let mut input = &[0u8][..];
+        assert_eq!(Varint::<u32>::deserialize(&mut input).unwrap(), Varint(0));
+
+        let mut input = &[127u8][..];
+        assert_eq!(Varint::<u32>::deserialize(&mut input).unwrap(), Varint(127));
+    }
+
+    #[test]
+    fn decodes_multi_byte_value() {
+        // 300 = 0b1_0010_1100, canonical LEB128 encoding is [0xAC, 0x02].
+        let mut input = &[0xAC, 0x02][..];
+        assert_eq!(Varint::<u32>::deserialize(&mut input).unwrap(), Varint(300));
+    }
+
+    #[test]
+    fn zigzag_decodes_signed_values() {
+        let mut input = &[0x00][..];
+        assert_eq!(Varint::<i32>::deserialize(&mut input).unwrap(), Varint(0));
+
+        let mut input = &[0x01][..];
+        assert_eq!(Varint::<i32>::deserialize(&mut input).unwrap(), Varint(-1));
+
+        let mut input = &[0x02][..];
+        assert_eq!(Varint::<i32>::deserialize(&mut input).unwrap(), Varint(1));
+    }
+
+    #[test]
+    fn rejects_non_canonical_trailing_zero_byte() {
+        // 5 canonically fits in one byte; re-encoding it with a
+        // redundant continuation byte followed by an all-zero
+        // terminator must be rejected instead of silently accepted.
+        let mut input = &[0x85, 0x00][..];
+        assert!(Varint::<u32>::deserialize(&mut input).is_err());
+    }
+
+    #[test]
+    fn rejects_value_overflowing_target_width() {
+        // Second group contributes a bit beyond u8's 8-bit width.
+        let mut input = &[0xFF, 0x02][..];
+        assert!(Varint::<u8>::deserialize(&mut input).is_err());
+    }
+
+    #[test]
+    fn rejects_too_many_continuation_bytes() {
+        let mut input = &[0x80u8, 0x80][..];
+        assert!(Varint::<u8>::deserialize(&mut input).is_err());
+    }
+}